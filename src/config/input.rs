@@ -8,12 +8,29 @@ use crate::function::ToolResult;
 use crate::utils::{base64_encode, sha256, AbortSignal};
 
 use anyhow::{bail, Context, Result};
+use image::{codecs::jpeg::JpegEncoder, AnimationDecoder, GenericImageView, ImageFormat};
+use once_cell::sync::Lazy;
 use path_absolutize::Absolutize;
-use std::{collections::HashMap, fs::File, io::Read, path::Path};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tempfile::NamedTempFile;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 const IMAGE_EXTS: [&str; 5] = ["png", "jpeg", "jpg", "webp", "gif"];
 const SUMMARY_MAX_WIDTH: usize = 80;
+const DEFAULT_IMAGE_MAX_LONG_SIDE: u32 = 2048;
+const DEFAULT_IMAGE_QUALITY: u8 = 85;
+const FOLLOW_PREFIX: &str = "follow:";
+const DEFAULT_CHUNK_TARGET_LEN: usize = 64 * 1024;
+const DEFAULT_CHUNK_MIN_LEN: usize = 16 * 1024;
+const DEFAULT_CHUNK_MAX_LEN: usize = 256 * 1024;
+const DEFAULT_CHUNK_MASK: u64 = (DEFAULT_CHUNK_TARGET_LEN as u64) - 1;
 
 #[derive(Debug, Clone)]
 pub struct Input {
@@ -26,6 +43,7 @@ pub struct Input {
     regenerate: bool,
     medias: Vec<String>,
     data_urls: HashMap<String, String>,
+    document_chunks: Vec<DocumentChunk>,
     tool_calls: Option<MessageContentToolCalls>,
     rag_name: Option<String>,
     role: Role,
@@ -46,6 +64,7 @@ impl Input {
             regenerate: false,
             medias: Default::default(),
             data_urls: Default::default(),
+            document_chunks: Default::default(),
             tool_calls: None,
             rag_name: None,
             role,
@@ -64,9 +83,21 @@ impl Input {
         let mut external_cmds = vec![];
         let mut local_paths = vec![];
         let mut remote_urls = vec![];
+        let mut remote_ssh_paths = vec![];
+        let mut follow_paths = vec![];
         let mut last_reply = None;
         let mut with_last_reply = false;
         for path in paths {
+            if let Some(follow_path) = path.strip_prefix(FOLLOW_PREFIX) {
+                raw_paths.push(path.clone());
+                follow_paths.push(follow_path.to_string());
+                continue;
+            }
+            if let Some(ssh_path) = parse_ssh_path(&path) {
+                raw_paths.push(path);
+                remote_ssh_paths.push(ssh_path);
+                continue;
+            }
             match resolve_local_path(&path) {
                 Some(v) => {
                     if v == "%%" {
@@ -88,10 +119,20 @@ impl Input {
                 }
             }
         }
-        let (files, medias, data_urls) =
-            load_documents(config, external_cmds, local_paths, remote_urls)
-                .await
-                .context("Failed to load files")?;
+        let (role, with_session, with_agent) = resolve_role(&config.read(), role);
+        let load_options = LoadOptions::from_config(&config.read());
+        let compress_image = resolve_compress_image_option(&load_options);
+        let (files, medias, data_urls) = load_documents(
+            config,
+            external_cmds,
+            local_paths,
+            remote_urls,
+            remote_ssh_paths,
+            compress_image,
+        )
+        .await
+        .context("Failed to load files")?;
+        let document_chunks = chunk_documents(&files, &resolve_chunk_option(&load_options));
         let mut texts = vec![];
         if !raw_text.is_empty() {
             texts.push(raw_text.to_string());
@@ -116,7 +157,15 @@ impl Input {
                 "\n============ {kind}: {path} ============\n{contents}"
             ));
         }
-        let (role, with_session, with_agent) = resolve_role(&config.read(), role);
+        for follow_path in follow_paths {
+            let tail = read_follow_tail(&follow_path)
+                .with_context(|| format!("Failed to follow file '{follow_path}'"))?;
+            if !tail.is_empty() {
+                texts.push(format!(
+                    "\n============ FOLLOW: {follow_path} ============\n{tail}"
+                ));
+            }
+        }
         Ok(Self {
             config: config.clone(),
             text: texts.join("\n"),
@@ -127,6 +176,7 @@ impl Input {
             regenerate: false,
             medias,
             data_urls,
+            document_chunks,
             tool_calls: Default::default(),
             rag_name: None,
             role,
@@ -225,6 +275,13 @@ impl Input {
         self.rag_name.as_deref()
     }
 
+    /// Content-defined chunks of the loaded files, deduplicated by content hash.
+    /// Callers can feed these into `use_embeddings` or iterate them map-reduce
+    /// style when the full document doesn't fit in one context window.
+    pub fn document_chunks(&self) -> &[DocumentChunk] {
+        &self.document_chunks
+    }
+
     pub fn merge_tool_results(mut self, output: String, tool_results: Vec<ToolResult>) -> Self {
         match self.tool_calls.as_mut() {
             Some(exist_tool_results) => {
@@ -251,7 +308,20 @@ impl Input {
         if model.no_system_message() {
             patch_system_message(&mut messages);
         }
-        model.guard_max_input_tokens(&messages)?;
+        if let Err(err) = model.guard_max_input_tokens(&messages) {
+            if self.document_chunks.is_empty() {
+                return Err(err);
+            }
+            let unique_chunks = self
+                .document_chunks
+                .iter()
+                .filter(|chunk| chunk.duplicate_of.is_none())
+                .count();
+            bail!(
+                "{err}\nThe loaded files were split into {} chunks ({unique_chunks} unique after dedup). Use `use_embeddings` or iterate `document_chunks()` map-reduce style instead of sending them all in one turn.",
+                self.document_chunks.len(),
+            );
+        }
         let temperature = self.role().temperature();
         let top_p = self.role().top_p();
         let functions = self.config.read().select_functions(self.role());
@@ -405,6 +475,8 @@ async fn load_documents(
     external_cmds: Vec<String>,
     local_paths: Vec<String>,
     remote_urls: Vec<String>,
+    remote_ssh_paths: Vec<SshLocation>,
+    compress_image: CompressImageOption,
 ) -> Result<(
     Vec<(&'static str, String, String)>,
     Vec<String>,
@@ -427,7 +499,7 @@ async fn load_documents(
     let loaders = config.read().document_loaders.clone();
     for file_path in local_files {
         if is_image(&file_path) {
-            let contents = read_media_to_data_url(&file_path)
+            let contents = read_media_to_data_url(&file_path, &compress_image)
                 .with_context(|| format!("Unable to read media file '{file_path}'"))?;
             data_urls.insert(sha256(&contents), file_path);
             medias.push(contents)
@@ -450,6 +522,26 @@ async fn load_documents(
             files.push(("URL", file_url, contents));
         }
     }
+
+    for ssh_path in remote_ssh_paths {
+        let remote_files = fetch_ssh_files(ssh_path).await?;
+        for (remote_path, contents) in remote_files {
+            if is_image(&remote_path) {
+                let extension = get_patch_extension(&remote_path).unwrap_or_default();
+                let data_url = encode_image_to_data_url(&extension, contents, &compress_image)
+                    .with_context(|| format!("Unable to read media file '{remote_path}'"))?;
+                data_urls.insert(sha256(&data_url), remote_path);
+                medias.push(data_url);
+            } else {
+                let tmp_file = stage_remote_file(&remote_path, &contents)?;
+                let document = load_file(&loaders, &tmp_file.path().to_string_lossy())
+                    .await
+                    .with_context(|| format!("Unable to read file '{remote_path}'"))?;
+                files.push(("FILE", remote_path, document.contents));
+            }
+        }
+    }
+
     Ok((files, medias, data_urls))
 }
 
@@ -465,16 +557,85 @@ pub fn resolve_data_url(data_urls: &HashMap<String, String>, data_url: String) -
     }
 }
 
+fn expand_tilde(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(file), Some(home)) => home.join(file).display().to_string(),
+        _ => path.to_string(),
+    }
+}
+
 fn resolve_local_path(path: &str) -> Option<String> {
     if is_url(path) {
         return None;
     }
-    let new_path = if let (Some(file), Some(home)) = (path.strip_prefix("~/"), dirs::home_dir()) {
-        home.join(file).display().to_string()
-    } else {
-        path.to_string()
+    Some(expand_tilde(path))
+}
+
+/// In-memory cache of the last-read byte offset of each followed file,
+/// seeded from and written through to `follow_offsets_path()` so the offset
+/// survives across turns in one session as well as across the separate
+/// one-shot processes a `aichat -f follow:... "..."` invocation runs in.
+static FOLLOW_OFFSETS: Lazy<Mutex<HashMap<String, u64>>> =
+    Lazy::new(|| Mutex::new(load_follow_offsets()));
+
+fn follow_offsets_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("aichat").join("follow_offsets.txt"))
+}
+
+fn load_follow_offsets() -> HashMap<String, u64> {
+    let Some(path) = follow_offsets_path() else {
+        return HashMap::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (offset, key) = line.split_once('\t')?;
+            Some((key.to_string(), offset.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Best-effort: a failure to persist just means the next invocation resumes
+/// from the last offset that was saved (or from the start of the file if none
+/// was), not a fatal error.
+fn save_follow_offsets(offsets: &HashMap<String, u64>) {
+    let Some(path) = follow_offsets_path() else {
+        return;
     };
-    Some(new_path)
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let serialized: String = offsets.iter().map(|(k, v)| format!("{v}\t{k}\n")).collect();
+    let _ = std::fs::write(path, serialized);
+}
+
+/// Reads the content appended to `path` since it was last followed, like `tail -f`.
+/// Restarts from the beginning when the file has shrunk (truncation or log rotation).
+fn read_follow_tail(path: &str) -> Result<String> {
+    let path = expand_tilde(path);
+    let key = Path::new(&path)
+        .absolutize()
+        .map(|v| v.display().to_string())
+        .unwrap_or_else(|_| path.clone());
+
+    let mut file = File::open(&path).with_context(|| format!("Unable to read file '{path}'"))?;
+    let size = file.metadata()?.len();
+
+    let mut offsets = FOLLOW_OFFSETS.lock().unwrap();
+    let offset = offsets.get(&key).copied().unwrap_or(0);
+    let offset = if size < offset { 0 } else { offset };
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut tail = String::new();
+    file.read_to_string(&mut tail)
+        .with_context(|| format!("Unable to read file '{path}'"))?;
+
+    offsets.insert(key, size);
+    save_follow_offsets(&offsets);
+    Ok(tail)
 }
 
 fn is_image(path: &str) -> bool {
@@ -483,21 +644,556 @@ fn is_image(path: &str) -> bool {
         .unwrap_or_default()
 }
 
-fn read_media_to_data_url(image_path: &str) -> Result<String> {
+/// Image-compression and document-chunking knobs read off `Config`, bundled
+/// here so `load_documents`/`chunk_documents` don't each need their own lock
+/// on the config.
+#[derive(Debug, Clone, Default)]
+struct LoadOptions {
+    compress_images: bool,
+    image_max_long_side: Option<u32>,
+    document_chunk_min_len: Option<usize>,
+    document_chunk_max_len: Option<usize>,
+}
+
+impl LoadOptions {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            compress_images: config.compress_images,
+            image_max_long_side: config.image_max_long_side,
+            document_chunk_min_len: config.document_chunk_min_len,
+            document_chunk_max_len: config.document_chunk_max_len,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CompressImageOption {
+    enabled: bool,
+    max_long_side: u32,
+    quality: u8,
+}
+
+impl Default for CompressImageOption {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_long_side: DEFAULT_IMAGE_MAX_LONG_SIDE,
+            quality: DEFAULT_IMAGE_QUALITY,
+        }
+    }
+}
+
+fn resolve_compress_image_option(load_options: &LoadOptions) -> CompressImageOption {
+    if !load_options.compress_images {
+        return CompressImageOption {
+            enabled: false,
+            ..Default::default()
+        };
+    }
+    CompressImageOption {
+        enabled: true,
+        max_long_side: load_options
+            .image_max_long_side
+            .unwrap_or(DEFAULT_IMAGE_MAX_LONG_SIDE),
+        quality: DEFAULT_IMAGE_QUALITY,
+    }
+}
+
+fn read_media_to_data_url(image_path: &str, compress: &CompressImageOption) -> Result<String> {
+    let mut file = File::open(image_path)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
     let extension = get_patch_extension(image_path).unwrap_or_default();
-    let mime_type = match extension.as_str() {
+    encode_image_to_data_url(&extension, buffer, compress)
+}
+
+/// Scales `(width, height)` down so its long side is `max_long_side`, keeping
+/// the aspect ratio. Callers already check `long_side > max_long_side` before
+/// calling, so this always shrinks.
+fn resize_dimensions(width: u32, height: u32, max_long_side: u32) -> (u32, u32) {
+    let long_side = width.max(height);
+    let scale = max_long_side as f64 / long_side as f64;
+    let new_width = ((width as f64 * scale).round() as u32).max(1);
+    let new_height = ((height as f64 * scale).round() as u32).max(1);
+    (new_width, new_height)
+}
+
+fn encode_image_to_data_url(
+    extension: &str,
+    buffer: Vec<u8>,
+    compress: &CompressImageOption,
+) -> Result<String> {
+    let source_mime_type = match extension {
         "png" => "image/png",
         "jpg" | "jpeg" => "image/jpeg",
         "webp" => "image/webp",
         "gif" => "image/gif",
         _ => bail!("Unexpected media type"),
     };
-    let mut file = File::open(image_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
 
-    let encoded_image = base64_encode(buffer);
-    let data_url = format!("data:{};base64,{}", mime_type, encoded_image);
+    if !compress.enabled || (extension == "gif" && is_animated_gif(&buffer)) {
+        let encoded_image = base64_encode(buffer);
+        return Ok(format!("data:{source_mime_type};base64,{encoded_image}"));
+    }
+
+    let img = match image::load_from_memory(&buffer) {
+        Ok(img) => img,
+        Err(_) => {
+            // Fall back to passing the original bytes through untouched if decoding fails.
+            let encoded_image = base64_encode(buffer);
+            return Ok(format!("data:{source_mime_type};base64,{encoded_image}"));
+        }
+    };
+
+    let (width, height) = img.dimensions();
+    let long_side = width.max(height);
+    if long_side <= compress.max_long_side {
+        // Already within budget: pass the original bytes through untouched
+        // rather than lossily re-encoding an image that didn't need resizing.
+        let encoded_image = base64_encode(buffer);
+        return Ok(format!("data:{source_mime_type};base64,{encoded_image}"));
+    }
+    let (new_width, new_height) = resize_dimensions(width, height, compress.max_long_side);
+    let img = img.resize(new_width, new_height, image::imageops::FilterType::Lanczos3);
+
+    let mut output = Cursor::new(Vec::new());
+    let mime_type = if img.color().has_alpha() {
+        img.write_to(&mut output, ImageFormat::Png)
+            .context("Failed to encode image as PNG")?;
+        "image/png"
+    } else {
+        JpegEncoder::new_with_quality(&mut output, compress.quality)
+            .encode_image(&img)
+            .context("Failed to encode image as JPEG")?;
+        "image/jpeg"
+    };
+
+    let encoded_image = base64_encode(output.into_inner());
+    Ok(format!("data:{mime_type};base64,{encoded_image}"))
+}
+
+fn is_animated_gif(buffer: &[u8]) -> bool {
+    let decoder = match image::codecs::gif::GifDecoder::new(Cursor::new(buffer)) {
+        Ok(decoder) => decoder,
+        Err(_) => return false,
+    };
+    decoder.into_frames().take(2).count() > 1
+}
+
+#[derive(Debug, Clone)]
+struct SshLocation {
+    user: Option<String>,
+    host: String,
+    port: u16,
+    path: String,
+}
 
-    Ok(data_url)
+/// Parses `sftp://[user@]host[:port]/path` and `ssh://[user@]host[:port]/path`.
+fn parse_ssh_path(path: &str) -> Option<SshLocation> {
+    let rest = path
+        .strip_prefix("sftp://")
+        .or_else(|| path.strip_prefix("ssh://"))?;
+    let (authority, remote_path) = rest.split_once('/')?;
+    let remote_path = format!("/{remote_path}");
+    let (user, host_port) = match authority.split_once('@') {
+        Some((user, host_port)) => (Some(user.to_string()), host_port),
+        None => (None, authority),
+    };
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) => (host.to_string(), port.parse().ok()?),
+        None => (host_port.to_string(), 22),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(SshLocation {
+        user,
+        host,
+        port,
+        path: remote_path,
+    })
+}
+
+/// Writes fetched remote bytes to a securely-created, uniquely named temp
+/// file that keeps the original extension, so `load_file` dispatches to the
+/// same document loader it would use for a local file of that type. Unlike a
+/// predictable content-addressed path, `NamedTempFile` is created with
+/// owner-only permissions and a random name, and removes itself on drop, so
+/// other local users can't race to read or preempt it.
+fn stage_remote_file(remote_path: &str, contents: &[u8]) -> Result<NamedTempFile> {
+    let extension = get_patch_extension(remote_path).unwrap_or_default();
+    let suffix = match extension.is_empty() {
+        true => String::new(),
+        false => format!(".{extension}"),
+    };
+    let mut tmp_file = tempfile::Builder::new()
+        .prefix("aichat-ssh-")
+        .suffix(&suffix)
+        .tempfile()
+        .with_context(|| format!("Unable to stage remote file '{remote_path}'"))?;
+    tmp_file
+        .write_all(contents)
+        .with_context(|| format!("Unable to stage remote file '{remote_path}'"))?;
+    Ok(tmp_file)
+}
+
+/// Opens an SSH/SFTP session and reads a single remote file, or every regular
+/// file in a remote directory when the path ends with a trailing `/`.
+async fn fetch_ssh_files(location: SshLocation) -> Result<Vec<(String, Vec<u8>)>> {
+    tokio::task::spawn_blocking(move || fetch_ssh_files_blocking(&location))
+        .await
+        .context("SSH task panicked")?
+}
+
+fn fetch_ssh_files_blocking(location: &SshLocation) -> Result<Vec<(String, Vec<u8>)>> {
+    let session = open_ssh_session(location)
+        .with_context(|| format!("Failed to connect to '{}@{}'", display_user(location), location.host))?;
+    let sftp = session
+        .sftp()
+        .context("Failed to start SFTP subsystem")?;
+
+    if location.path.ends_with('/') {
+        let entries = sftp
+            .readdir(Path::new(&location.path))
+            .with_context(|| format!("Failed to list remote directory '{}'", location.path))?;
+        let mut results = vec![];
+        for (entry_path, stat) in entries {
+            if !stat.is_file() {
+                continue;
+            }
+            let entry_path = entry_path.display().to_string();
+            let mut file = sftp
+                .open(Path::new(&entry_path))
+                .with_context(|| format!("Failed to open remote file '{entry_path}'"))?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+            results.push((format!("sftp://{}{}", location.host, entry_path), contents));
+        }
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(results)
+    } else {
+        let mut file = sftp
+            .open(Path::new(&location.path))
+            .with_context(|| format!("Failed to open remote file '{}'", location.path))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        Ok(vec![(
+            format!("sftp://{}{}", location.host, location.path),
+            contents,
+        )])
+    }
+}
+
+fn open_ssh_session(location: &SshLocation) -> Result<ssh2::Session> {
+    let tcp = TcpStream::connect((location.host.as_str(), location.port))
+        .with_context(|| format!("Failed to connect to '{}:{}'", location.host, location.port))?;
+    let mut session = ssh2::Session::new().context("Failed to create SSH session")?;
+    session.set_tcp_stream(tcp);
+    session.handshake().context("SSH handshake failed")?;
+
+    let user = display_user(location);
+    if !authenticate_with_agent(&session, &user) && !authenticate_with_default_keys(&session, &user)
+    {
+        bail!("Failed to authenticate to '{user}@{}'", location.host);
+    }
+    Ok(session)
+}
+
+fn authenticate_with_agent(session: &ssh2::Session, user: &str) -> bool {
+    let mut agent = match session.agent() {
+        Ok(agent) => agent,
+        Err(_) => return false,
+    };
+    if agent.connect().is_err() || agent.list_identities().is_err() {
+        return false;
+    }
+    let identities = match agent.identities() {
+        Ok(identities) => identities,
+        Err(_) => return false,
+    };
+    identities
+        .iter()
+        .any(|identity| agent.userauth(user, identity).is_ok())
+}
+
+fn authenticate_with_default_keys(session: &ssh2::Session, user: &str) -> bool {
+    let Some(home) = dirs::home_dir() else {
+        return false;
+    };
+    let ssh_dir = home.join(".ssh");
+    ["id_ed25519", "id_ecdsa", "id_rsa"].iter().any(|name| {
+        let private_key = ssh_dir.join(name);
+        private_key.exists() && session.userauth_pubkey_file(user, None, &private_key, None).is_ok()
+    })
+}
+
+fn display_user(location: &SshLocation) -> String {
+    location.user.clone().unwrap_or_else(|| {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("LOGNAME"))
+            .unwrap_or_else(|_| "root".to_string())
+    })
+}
+
+/// A content-defined chunk of a loaded file. Chunks that repeat a previously
+/// seen chunk's content (common in concatenated logs or repeated headers)
+/// have their content replaced with a short marker and `duplicate_of` set to
+/// the index of the first occurrence.
+#[derive(Debug, Clone)]
+pub struct DocumentChunk {
+    pub file_kind: &'static str,
+    pub file_path: String,
+    pub index: usize,
+    pub content: String,
+    pub duplicate_of: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChunkOption {
+    min_len: usize,
+    max_len: usize,
+    mask: u64,
+}
+
+impl Default for ChunkOption {
+    fn default() -> Self {
+        Self {
+            min_len: DEFAULT_CHUNK_MIN_LEN,
+            max_len: DEFAULT_CHUNK_MAX_LEN,
+            mask: DEFAULT_CHUNK_MASK,
+        }
+    }
+}
+
+fn resolve_chunk_option(load_options: &LoadOptions) -> ChunkOption {
+    ChunkOption {
+        min_len: load_options.document_chunk_min_len.unwrap_or(DEFAULT_CHUNK_MIN_LEN),
+        max_len: load_options.document_chunk_max_len.unwrap_or(DEFAULT_CHUNK_MAX_LEN),
+        ..Default::default()
+    }
+}
+
+/// Splits oversized file contents into content-defined chunks and replaces the
+/// content of chunks that duplicate one already seen, across all loaded files,
+/// with a short marker.
+fn chunk_documents(
+    files: &[(&'static str, String, String)],
+    opts: &ChunkOption,
+) -> Vec<DocumentChunk> {
+    let mut chunks = vec![];
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (kind, path, contents) in files {
+        if contents.len() <= opts.max_len {
+            push_chunk(&mut chunks, &mut seen, kind, path, contents.clone());
+            continue;
+        }
+        for piece in gear_hash_chunks(contents, opts) {
+            push_chunk(&mut chunks, &mut seen, kind, path, piece.to_string());
+        }
+    }
+    chunks
+}
+
+fn push_chunk(
+    chunks: &mut Vec<DocumentChunk>,
+    seen: &mut HashMap<String, usize>,
+    file_kind: &'static str,
+    file_path: &str,
+    content: String,
+) {
+    let index = chunks.len();
+    let hash = sha256(&content);
+    match seen.get(&hash) {
+        Some(&first_index) => chunks.push(DocumentChunk {
+            file_kind,
+            file_path: file_path.to_string(),
+            index,
+            content: format!("(duplicate of chunk {first_index} omitted)"),
+            duplicate_of: Some(first_index),
+        }),
+        None => {
+            seen.insert(hash, index);
+            chunks.push(DocumentChunk {
+                file_kind,
+                file_path: file_path.to_string(),
+                index,
+                content,
+                duplicate_of: None,
+            });
+        }
+    }
+}
+
+/// A table of pseudo-random 64-bit constants indexed by byte value, used to
+/// advance the rolling gear-hash fingerprint below.
+static GEAR_TABLE: Lazy<[u64; 256]> = Lazy::new(|| {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    for slot in table.iter_mut() {
+        seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        *slot = z;
+    }
+    table
+});
+
+/// Cuts `data` into variable-length pieces using a gear-hash content-defined
+/// boundary: a 64-bit rolling fingerprint is advanced one byte at a time and a
+/// boundary is drawn once the chunk has reached `min_len` and
+/// `fingerprint & mask == 0`, which averages out to chunks of roughly
+/// `min_len` plus `mask + 1` bytes. A hard cut at `max_len` keeps pathological inputs (e.g.
+/// runs of the same byte) from producing one giant chunk. Candidate
+/// boundaries are snapped forward to the next `char` boundary so a multi-byte
+/// UTF-8 character is never split across two chunks.
+fn gear_hash_chunks(data: &str, opts: &ChunkOption) -> Vec<&str> {
+    let bytes = data.as_bytes();
+    if bytes.is_empty() {
+        return vec![];
+    }
+    let table = &*GEAR_TABLE;
+    let mut chunks = vec![];
+    let mut start = 0usize;
+    let mut fingerprint: u64 = 0;
+    let mut i = 0usize;
+    while i < bytes.len() {
+        fingerprint = fingerprint
+            .wrapping_shl(1)
+            .wrapping_add(table[bytes[i] as usize]);
+        let len = i - start + 1;
+        if len >= opts.max_len || (len >= opts.min_len && fingerprint & opts.mask == 0) {
+            let mut end = i + 1;
+            while end < bytes.len() && !data.is_char_boundary(end) {
+                end += 1;
+            }
+            chunks.push(&data[start..end]);
+            start = end;
+            i = end;
+            fingerprint = 0;
+            continue;
+        }
+        i += 1;
+    }
+    if start < bytes.len() {
+        chunks.push(&data[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_path() {
+        let loc = parse_ssh_path("sftp://build.example.com/var/log/ci.log").unwrap();
+        assert_eq!(loc.user, None);
+        assert_eq!(loc.host, "build.example.com");
+        assert_eq!(loc.port, 22);
+        assert_eq!(loc.path, "/var/log/ci.log");
+
+        let loc = parse_ssh_path("ssh://deploy@10.0.0.5:2222/home/deploy/logs/").unwrap();
+        assert_eq!(loc.user.as_deref(), Some("deploy"));
+        assert_eq!(loc.host, "10.0.0.5");
+        assert_eq!(loc.port, 2222);
+        assert_eq!(loc.path, "/home/deploy/logs/");
+
+        assert!(parse_ssh_path("https://example.com/file.txt").is_none());
+        assert!(parse_ssh_path("/local/path").is_none());
+        assert!(parse_ssh_path("sftp://").is_none());
+    }
+
+    #[test]
+    fn test_read_follow_tail_handles_growth_and_truncation() {
+        let path = std::env::temp_dir().join(format!(
+            "aichat-follow-test-{}",
+            std::process::id()
+        ));
+        std::fs::write(&path, "first\n").unwrap();
+        let path_str = path.to_string_lossy().to_string();
+
+        let tail = read_follow_tail(&path_str).unwrap();
+        assert_eq!(tail, "first\n");
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        use std::io::Write;
+        file.write_all(b"second\n").unwrap();
+        drop(file);
+        let tail = read_follow_tail(&path_str).unwrap();
+        assert_eq!(tail, "second\n");
+
+        // Simulate truncation/rotation: the file shrinks below the stored offset.
+        std::fs::write(&path, "restarted\n").unwrap();
+        let tail = read_follow_tail(&path_str).unwrap();
+        assert_eq!(tail, "restarted\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_gear_hash_chunks_preserves_char_boundaries() {
+        // Force tiny chunk sizes so cuts land in the middle of the multi-byte
+        // characters below, then verify every returned chunk is valid UTF-8
+        // and that the pieces concatenate back to the original string.
+        let opts = ChunkOption {
+            min_len: 1,
+            max_len: 4,
+            mask: 0,
+        };
+        let data = "a😀b日本語c🎉d";
+        let chunks = gear_hash_chunks(data, &opts);
+        assert!(!chunks.is_empty());
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[test]
+    fn test_resize_dimensions_preserves_aspect_ratio() {
+        assert_eq!(resize_dimensions(1000, 500, 100), (100, 50));
+        assert_eq!(resize_dimensions(500, 1000, 100), (50, 100));
+    }
+
+    #[test]
+    fn test_encode_image_to_data_url_keeps_alpha_as_png() {
+        let img = image::RgbaImage::from_fn(20, 10, |x, _y| image::Rgba([x as u8, 0, 0, 128]));
+        let mut buffer = Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(img)
+            .write_to(&mut buffer, ImageFormat::Png)
+            .unwrap();
+        let compress = CompressImageOption {
+            enabled: true,
+            max_long_side: 5,
+            quality: DEFAULT_IMAGE_QUALITY,
+        };
+        let data_url =
+            encode_image_to_data_url("png", buffer.into_inner(), &compress).unwrap();
+        assert!(data_url.starts_with("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn test_encode_image_to_data_url_passes_through_animated_gif() {
+        let mut buffer = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut buffer);
+            for _ in 0..2 {
+                let frame = image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]));
+                encoder.encode_frame(image::Frame::new(frame)).unwrap();
+            }
+        }
+        let compress = CompressImageOption {
+            enabled: true,
+            max_long_side: 1,
+            quality: DEFAULT_IMAGE_QUALITY,
+        };
+        let data_url = encode_image_to_data_url("gif", buffer.clone(), &compress).unwrap();
+        assert_eq!(
+            data_url,
+            format!("data:image/gif;base64,{}", base64_encode(buffer))
+        );
+    }
 }